@@ -1,5 +1,8 @@
 extern crate num;
 extern crate image;
+extern crate rand;
+extern crate clap;
+extern crate indicatif;
 
 use num::Complex;
 use std::str::FromStr;
@@ -7,24 +10,197 @@ use image::ColorType;
 use image::png::PNGEncoder;
 use std::fs::File;
 use std::io::Write;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+use rand::Rng;
+use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
 
-/// Cherche à savoir si `c` est dans l'ensemble de Mandelbrot, en vérifiant
-/// si la suite de complexe `z_{n+1} = z_n^2 + c` ne diverge pas.
+/// Variante de l'ensemble de Mandelbrot à calculer, c'est-à-dire la suite
+/// itérée `z_{n+1} = f(z_n) + c` (ou apparentée) qui détermine
+/// l'appartenance d'un point `c` à l'ensemble affiché.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FractalKind {
+    /// `z_{n+1} = z_n^2 + c`
+    Mandelbrot,
+    /// `z_{n+1} = z_n^3 + c`
+    Mandelbrot3,
+    /// `z_{n+1} = (|Re z_n| + |Im z_n| i)^2 + c`
+    BurningShip,
+}
+
+impl FromStr for FractalKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mandelbrot" => Ok(FractalKind::Mandelbrot),
+            "mandelbrot3" => Ok(FractalKind::Mandelbrot3),
+            "burning_ship" => Ok(FractalKind::BurningShip),
+            _ => Err(format!("fractale inconnue : \"{}\"", s)),
+        }
+    }
+}
+
+#[test]
+fn test_fractal_kind_from_str() {
+    assert_eq!("mandelbrot".parse(), Ok(FractalKind::Mandelbrot));
+    assert_eq!("mandelbrot3".parse(), Ok(FractalKind::Mandelbrot3));
+    assert_eq!("burning_ship".parse(), Ok(FractalKind::BurningShip));
+    assert!("autre_chose".parse::<FractalKind>().is_err());
+}
+
+/// Applique une itération de la suite désignée par `fractal` au point `z`,
+/// pour le paramètre `c`.
+fn iterer(z: Complex<f64>, c: Complex<f64>, fractal: FractalKind) -> Complex<f64> {
+    match fractal {
+        FractalKind::Mandelbrot => z * z + c,
+        FractalKind::Mandelbrot3 => z * z * z + c,
+        FractalKind::BurningShip => {
+            let z = Complex { re: z.re.abs(), im: z.im.abs() };
+            z * z + c
+        }
+    }
+}
+
+/// Rayon (au-delà de 2) à partir duquel on considère que `|z|` est
+/// suffisamment loin du seuil d'échappement pour que le calcul de `mu`
+/// dans `escape_time_smooth` soit précis.
+const RAYON_FUITE: f64 = 65536.0; // 2^16
+
+/// Nombre d'itérations supplémentaires effectuées après la détection de
+/// l'échappement, pour la même raison.
+const ITERATIONS_SUPP: u32 = 3;
+
+/// Calcule le temps d'échappement de `c` pour la suite désignée par
+/// `fractal` sous la forme d'un nombre d'itérations fractionnaire, pour
+/// permettre une coloration continue plutôt que des bandes entières.
+/// Renvoie `None` si l'orbite ne s'échappe pas en `limit` itérations.
 ///
-/// Si la suite diverge, renvoie `Some(i)`, où `i` est le nombre d'itérations
-/// avant que la suite ne diverge. Si la suite ne diverge pas après `MAX_ITER`,
-/// renvoie `None`.
-fn escape_time(c: Complex<f64>, limit: u32) -> Option<u32> {
+/// Dès que l'orbite dépasse `RAYON_FUITE`, on poursuit encore
+/// `ITERATIONS_SUPP` itérations avant de calculer
+/// `mu = i + 1 - ln(ln|z|) / ln 2`, ce qui rend `mu` plus précis. `i` doit
+/// compter ces itérations supplémentaires, sinon `mu` est systématiquement
+/// décalé vers le négatif. Pour les points qui s'échappent dès les toutes
+/// premières itérations, la formule reste légèrement négative même une fois
+/// `i` correctement compté ; on ramène alors `mu` à 0, qui reste la valeur
+/// la plus proche mathématiquement.
+fn escape_time_smooth(c: Complex<f64>, limit: u32, fractal: FractalKind) -> Option<f64> {
     let mut z = Complex { re: 0.0, im: 0.0 };
     for i in 0..limit {
-        z = z * z + c;
-        if z.norm_sqr() > 4.0 {
-            return Some(i);
+        z = iterer(z, c, fractal);
+        if z.norm_sqr() > RAYON_FUITE * RAYON_FUITE {
+            let mut i = i;
+            for _ in 0..ITERATIONS_SUPP {
+                z = iterer(z, c, fractal);
+                i += 1;
+            }
+            let mu = i as f64 + 1.0 - (z.norm().ln().ln() / 2f64.ln());
+            return Some(mu.max(0.0));
         }
     }
     None
 }
 
+#[test]
+fn test_escape_time_smooth_non_negative() {
+    for c in [Complex { re: 2.0, im: 0.0 },
+              Complex { re: 5.0, im: 5.0 },
+              Complex { re: -2.5, im: 0.0 }] {
+        let mu = escape_time_smooth(c, 255, FractalKind::Mandelbrot)
+            .expect("ces points divergent et doivent s'échapper");
+        assert!(mu >= 0.0, "mu devrait être positif ou nul pour c = {:?}, obtenu {}", c, mu);
+    }
+}
+
+/// Palette utilisée pour convertir le nombre d'itérations fractionnaire
+/// `mu` renvoyé par `escape_time_smooth` en une couleur RGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Palette {
+    /// Dégradé classique du bleu vers l'orange.
+    BleuOrange,
+    /// Rampe façon feu, du noir au jaune en passant par le rouge.
+    Feu,
+    /// Teinte cyclique en fonction de `mu` (roue chromatique HSV).
+    Hsv,
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bleu_orange" => Ok(Palette::BleuOrange),
+            "feu" => Ok(Palette::Feu),
+            "hsv" => Ok(Palette::Hsv),
+            _ => Err(format!("palette inconnue : \"{}\"", s)),
+        }
+    }
+}
+
+#[test]
+fn test_palette_from_str() {
+    assert_eq!("bleu_orange".parse(), Ok(Palette::BleuOrange));
+    assert_eq!("feu".parse(), Ok(Palette::Feu));
+    assert_eq!("hsv".parse(), Ok(Palette::Hsv));
+    assert!("autre_chose".parse::<Palette>().is_err());
+}
+
+/// Convertit une couleur teinte/saturation/valeur (`h` en degrés 0..360,
+/// `s` et `v` en 0..1) en triplet RGB 8 bits.
+fn hsv_vers_rgb(h: f64, s: f64, v: f64) -> (u8, u8, u8) {
+    let c = v * s;
+    let h_prime = h / 60.0;
+    let x = c * (1.0 - (h_prime % 2.0 - 1.0).abs());
+    let (r1, g1, b1) = match h_prime as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    (((r1 + m) * 255.0) as u8, ((g1 + m) * 255.0) as u8, ((b1 + m) * 255.0) as u8)
+}
+
+/// Transforme un nombre d'itérations fractionnaire `mu` (`None` pour un
+/// point intérieur, qui reste noir) en couleur RGB selon la `palette`
+/// choisie. `mu` est ramené à 0 s'il est négatif, pour rester défensif
+/// face à un appelant qui ne garantirait pas un nombre d'itérations
+/// bien formé.
+fn colorer(mu: Option<f64>, palette: Palette) -> (u8, u8, u8) {
+    let mu = match mu {
+        None => return (0, 0, 0),
+        Some(mu) => mu.max(0.0),
+    };
+
+    match palette {
+        Palette::BleuOrange => {
+            let t = (mu / 64.0).fract();
+            let bleu = (30.0, 60.0, 150.0);
+            let orange = (255.0, 140.0, 20.0);
+            (
+                (bleu.0 + (orange.0 - bleu.0) * t) as u8,
+                (bleu.1 + (orange.1 - bleu.1) * t) as u8,
+                (bleu.2 + (orange.2 - bleu.2) * t) as u8,
+            )
+        }
+        Palette::Feu => {
+            let t = (mu / 128.0).min(1.0);
+            (
+                (255.0 * t) as u8,
+                (200.0 * t * t) as u8,
+                (60.0 * t.powi(3)) as u8,
+            )
+        }
+        Palette::Hsv => {
+            let teinte = (mu * 4.0) % 360.0;
+            hsv_vers_rgb(teinte, 0.8, 1.0)
+        }
+    }
+}
+
 /// Analyse de la chaîne s en tant que paire de coordonnées
 /// du type "400x600" ou "1.0,0.5".
 /// Le format doit être <gauche><sep><droite>, avec <sep> le
@@ -100,35 +276,214 @@ fn test_pixel_en_point() {
                Complex { re: -0.5, im: -0.5 });
 }
 
+/// Paramètres de rendu qui ne varient pas d'une bande à l'autre, regroupés
+/// pour ne pas faire grossir indéfiniment la liste d'arguments de `render`.
+#[derive(Debug, Clone, Copy)]
+struct RenderConfig {
+    fractal: FractalKind,
+    palette: Palette,
+    iterations: u32,
+}
+
 /// Production dans un tampon de pixels d'un rectangle Mandelbrot.
 
-/// Bords indique la hauteur et la largeur du tampon pixels
-/// qui contient un pixel en nuance de gris par octet.
+/// Bords indique la hauteur et la largeur du tampon pixels, qui contient
+/// 3 octets (rouge, vert, bleu) par pixel.
 /// Les variables super_ga et infer_dr correspondent aux angles
 /// supérieur gauche et inférieur droit du rectangle du tampon.
+///
+/// La couleur de chaque pixel est dérivée du nombre d'itérations
+/// fractionnaire renvoyé par `escape_time_smooth` via la `palette` de
+/// `config`, ce qui évite les bandes visibles des comptes entiers. Les
+/// points intérieurs (`None`) restent noirs.
+///
+/// `progres` est incrémenté d'une unité par ligne terminée, pour que le
+/// thread principal puisse en suivre l'avancement.
 fn render(pixels: &mut [u8],
           bords: (usize, usize),
           super_ga: Complex<f64>,
-          infer_dr: Complex<f64>)
+          infer_dr: Complex<f64>,
+          config: &RenderConfig,
+          progres: &AtomicU64)
 {
-    assert!(pixels.len() == bords.0 * bords.1);
-    
+    assert!(pixels.len() == 3 * bords.0 * bords.1);
+
     for ligne in 0..bords.1 {
         for colonne in 0..bords.0 {
             let point = pixel_en_point(bords, (colonne, ligne),
                                        super_ga, infer_dr);
-            pixels[ligne * bords.0 + colonne] =
-                match escape_time(point, 255) {
-                    None => 0,
-                    Some(i) => 255 - i as u8
-                };
+            let mu = escape_time_smooth(point, config.iterations, config.fractal);
+            let (r, g, b) = colorer(mu, config.palette);
+            let i = (ligne * bords.0 + colonne) * 3;
+            pixels[i] = r;
+            pixels[i + 1] = g;
+            pixels[i + 2] = b;
         }
+        progres.fetch_add(1, Ordering::Relaxed);
     }
 }
 
-/// Ecrit le tampon 'pixels', de dimensions 'bords', dans le
-/// fichier 'nomfic'.
-fn ecrire_image(nomfic: &str, pixels: &[u8], bords: (usize, usize))
+/// Production d'un rendu Buddhabrot : au lieu de tester l'appartenance de
+/// chaque pixel à l'ensemble, on tire `echantillons` points `c` au hasard
+/// sur la zone affichée (élargie d'une marge), on ne garde que ceux dont
+/// l'orbite `z_{n+1} = z_n^2 + c` s'échappe avant `limit` itérations, et
+/// pour chacun on rejoue l'orbite depuis `z = 0` en incrémentant, pour
+/// chaque `z_n` tombant dans le rectangle affiché, le compteur du pixel
+/// correspondant.
+///
+/// `compteurs` est partagé entre les bandes de threads : chaque case est
+/// un `AtomicU32` pour que les incréments concurrents restent corrects.
+///
+/// `progres` est incrémenté d'une unité par échantillon traité (qu'il se
+/// soit échappé ou non), pour que le thread principal puisse en suivre
+/// l'avancement.
+fn render_buddhabrot(compteurs: &[AtomicU32],
+                     bords: (usize, usize),
+                     super_ga: Complex<f64>,
+                     infer_dr: Complex<f64>,
+                     echantillons: usize,
+                     limit: u32,
+                     progres: &AtomicU64)
+{
+    assert!(compteurs.len() == bords.0 * bords.1);
+
+    const MARGE: f64 = 0.5;
+    let large = infer_dr.re - super_ga.re;
+    let haute = super_ga.im - infer_dr.im;
+    let zone_sup_ga = Complex { re: super_ga.re - MARGE * large, im: super_ga.im + MARGE * haute };
+    let zone_infer_dr = Complex { re: infer_dr.re + MARGE * large, im: infer_dr.im - MARGE * haute };
+
+    let mut rng = rand::thread_rng();
+    let mut orbite = Vec::with_capacity(limit as usize);
+
+    for _ in 0..echantillons {
+        let c = Complex {
+            re: rng.gen_range(zone_sup_ga.re, zone_infer_dr.re),
+            im: rng.gen_range(zone_infer_dr.im, zone_sup_ga.im),
+        };
+
+        orbite.clear();
+        let mut z = Complex { re: 0.0, im: 0.0 };
+        let mut echappe = false;
+        for _ in 0..limit {
+            z = z * z + c;
+            orbite.push(z);
+            if z.norm_sqr() > 4.0 {
+                echappe = true;
+                break;
+            }
+        }
+
+        progres.fetch_add(1, Ordering::Relaxed);
+
+        if !echappe {
+            continue;
+        }
+
+        for z in &orbite {
+            if z.re < super_ga.re || z.re >= infer_dr.re
+                || z.im > super_ga.im || z.im <= infer_dr.im {
+                continue;
+            }
+            let colonne = ((z.re - super_ga.re) / large * bords.0 as f64) as usize;
+            let ligne = ((super_ga.im - z.im) / haute * bords.1 as f64) as usize;
+            if colonne < bords.0 && ligne < bords.1 {
+                compteurs[ligne * bords.0 + colonne].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Construit une barre de progression sur `total` unités, affichant le
+/// pourcentage et une estimation du temps restant.
+fn barre_progression(total: u64) -> ProgressBar {
+    let barre = ProgressBar::new(total);
+    barre.set_style(ProgressStyle::default_bar()
+        .template("{bar:40.cyan/blue} {percent}% ({pos}/{len}, ETA {eta})")
+        .unwrap()
+        .progress_chars("##-"));
+    barre
+}
+
+/// Normalise le tampon de compteurs d'un rendu Buddhabrot vers des octets
+/// 0..255, en ramenant le compteur le plus élevé à 255.
+fn normaliser_buddhabrot(compteurs: &[AtomicU32], pixels: &mut [u8]) {
+    assert!(compteurs.len() == pixels.len());
+
+    let max = compteurs.iter()
+        .map(|c| c.load(Ordering::Relaxed))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for (pixel, compteur) in pixels.iter_mut().zip(compteurs) {
+        let valeur = compteur.load(Ordering::Relaxed);
+        *pixel = (valeur as f64 / max as f64 * 255.0) as u8;
+    }
+}
+
+/// Conteneur de fichier utilisé pour écrire l'image de sortie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FormatImage {
+    /// PNG, via `PNGEncoder`.
+    Png,
+    /// PNM (PGM en nuances de gris, PPM en couleurs), sans dépendance de
+    /// compression : pratique pour les grandes images.
+    Pnm,
+}
+
+impl FromStr for FormatImage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(FormatImage::Png),
+            "pnm" => Ok(FormatImage::Pnm),
+            _ => Err(format!("format inconnu : \"{}\"", s)),
+        }
+    }
+}
+
+#[test]
+fn test_format_image_from_str() {
+    assert_eq!("png".parse(), Ok(FormatImage::Png));
+    assert_eq!("pnm".parse(), Ok(FormatImage::Pnm));
+    assert!("autre_chose".parse::<FormatImage>().is_err());
+}
+
+/// Déduit le format d'image de l'extension du nom de fichier 'nomfic'
+/// (`.pgm` ou `.ppm` pour le PNM, tout le reste pour le PNG).
+fn format_depuis_extension(nomfic: &str) -> FormatImage {
+    match nomfic.rsplit('.').next() {
+        Some("pgm") | Some("ppm") => FormatImage::Pnm,
+        _ => FormatImage::Png,
+    }
+}
+
+#[test]
+fn test_format_depuis_extension() {
+    assert_eq!(format_depuis_extension("mandel.png"), FormatImage::Png);
+    assert_eq!(format_depuis_extension("mandel.pgm"), FormatImage::Pnm);
+    assert_eq!(format_depuis_extension("mandel.ppm"), FormatImage::Pnm);
+    assert_eq!(format_depuis_extension("mandel"), FormatImage::Png);
+}
+
+/// Ecrit le tampon 'pixels', de dimensions 'bords' et de format de couleur
+/// 'couleur' (`ColorType::Gray(8)` pour le Buddhabrot, `ColorType::RGB(8)`
+/// pour un rendu en couleurs), dans le fichier 'nomfic', encodé selon
+/// 'format'.
+fn ecrire_image(nomfic: &str, pixels: &[u8], bords: (usize, usize),
+                couleur: ColorType, format: FormatImage)
+    -> Result<(), std::io::Error>
+{
+    match format {
+        FormatImage::Png => ecrire_png(nomfic, pixels, bords, couleur),
+        FormatImage::Pnm => ecrire_pnm(nomfic, pixels, bords, couleur),
+    }
+}
+
+/// Ecrit 'pixels' au format PNG dans 'nomfic'.
+fn ecrire_png(nomfic: &str, pixels: &[u8], bords: (usize, usize), couleur: ColorType)
     -> Result<(), std::io::Error>
 {
     let sortie = File::create(nomfic)?;
@@ -136,55 +491,176 @@ fn ecrire_image(nomfic: &str, pixels: &[u8], bords: (usize, usize))
     let encodeur = PNGEncoder::new(sortie);
     encodeur.encode(&pixels,
                     bords.0 as u32, bords.1 as u32,
-                    ColorType::Gray(8))?;
+                    couleur)?;
     Ok(())
 }
 
+/// Ecrit 'pixels' au format PNM (P5 en nuances de gris, P6 en couleurs)
+/// dans 'nomfic' : un en-tête ASCII suivi des octets de pixels bruts.
+fn ecrire_pnm(nomfic: &str, pixels: &[u8], bords: (usize, usize), couleur: ColorType)
+    -> Result<(), std::io::Error>
+{
+    let mut sortie = File::create(nomfic)?;
+
+    let magique = match couleur {
+        ColorType::Gray(8) => "P5",
+        ColorType::RGB(8) => "P6",
+        _ => panic!("ecrire_pnm ne gère que Gray(8) et RGB(8)"),
+    };
+    writeln!(sortie, "{}", magique)?;
+    writeln!(sortie, "{} {}", bords.0, bords.1)?;
+    writeln!(sortie, "255")?;
+    sortie.write_all(pixels)?;
+    Ok(())
+}
+
+/// Générateur de fractales de type Mandelbrot, avec rendu parallèle,
+/// coloration continue et plusieurs palettes.
+#[derive(Parser, Debug)]
+#[clap(name = "mandelbrot", about = "Génère une image de fractale de type Mandelbrot")]
+struct Cli {
+    /// Fichier de sortie (PNG, ou PNM si `--format` le demande ou, en
+    /// l'absence de `--format`, si l'extension est `.pgm`/`.ppm`)
+    sortie: String,
+
+    /// Dimensions de l'image, au format LARGEURxHAUTEUR
+    pixels: String,
+
+    /// Coin supérieur gauche du plan complexe affiché, au format RE,IM
+    #[clap(allow_hyphen_values = true)]
+    coin_sup_ga: String,
+
+    /// Coin inférieur droit du plan complexe affiché, au format RE,IM
+    #[clap(allow_hyphen_values = true)]
+    coin_infer_dr: String,
+
+    /// Fractale à calculer
+    #[clap(long, default_value = "mandelbrot")]
+    fractal: FractalKind,
+
+    /// Palette de couleurs
+    #[clap(long, default_value = "bleu_orange")]
+    palette: Palette,
+
+    /// Nombre d'itérations maximum avant de considérer qu'un point n'est
+    /// pas divergent
+    #[clap(long, default_value = "255")]
+    iterations: u32,
+
+    /// Nombre maximal de bandes (threads) utilisées pour le rendu parallèle
+    #[clap(long, default_value = "32", value_parser = clap::value_parser!(usize).range(1..))]
+    threads: usize,
+
+    /// Calcule un Buddhabrot (accumulation des orbites échappées) au lieu
+    /// du rendu d'appartenance habituel
+    #[clap(long)]
+    buddhabrot: bool,
+
+    /// Nombre d'échantillons tirés au hasard en mode Buddhabrot
+    #[clap(long, default_value = "5000000")]
+    echantillons: usize,
+
+    /// Format du fichier de sortie ; par défaut déduit de l'extension de
+    /// `sortie` (`.pgm`/`.ppm` pour le PNM, tout le reste pour le PNG)
+    #[clap(long)]
+    format: Option<FormatImage>,
+}
+
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let cli = Cli::parse();
 
-    if args.len() != 5 {
-        writeln!(std::io::stderr(),
-                 "Usage: mandelbrot NOMFIC PIXELS SUPGA INFDR")
-            .unwrap();
-        writeln!(std::io::stderr(),
-                 "Exemple: {} mandel.png 1000x750 -1.20,0.35 -1,0.20",
-                 args[0])
-            .unwrap();
-        std::process::exit(1);
-    }
+    let format = cli.format.unwrap_or_else(|| format_depuis_extension(&cli.sortie));
 
-    let bords = analy_paire(&args[2], 'x')
+    let bords = analy_paire(&cli.pixels, 'x')
         .expect("Impossible d'analyser les dimensions de l'image");
-    let super_ga = analy_complex(&args[3])
+    let super_ga = analy_complex(&cli.coin_sup_ga)
         .expect("Impossible d'analyser le coin supérieur gauche");
-    let infer_dr = analy_complex(&args[4])
+    let infer_dr = analy_complex(&cli.coin_infer_dr)
         .expect("Impossible d'analyser le coin inférieur droit");
 
-    let mut pixels = vec![0; bords.0 * bords.1];
+    if cli.buddhabrot {
+        let compteurs: Vec<AtomicU32> =
+            (0..bords.0 * bords.1).map(|_| AtomicU32::new(0)).collect();
+        let progres = AtomicU64::new(0);
+        let barre = barre_progression(cli.echantillons as u64);
+
+        let echant_par_bande = cli.echantillons / cli.threads;
+        let reste = cli.echantillons % cli.threads;
 
-     
-    let exetrons = 32;
-    let lig_par_bande = bords.1 / exetrons+1;
+        crossbeam::scope(|spawner| {
+            for t in 0..cli.threads {
+                let echant_par_bande = echant_par_bande + if t < reste { 1 } else { 0 };
+                let compteurs = &compteurs;
+                let progres = &progres;
+                spawner.spawn(move || {
+                    render_buddhabrot(compteurs, bords, super_ga, infer_dr,
+                                      echant_par_bande, cli.iterations, progres)
+                });
+            }
+
+            spawner.spawn(|| {
+                while !barre.is_finished() {
+                    let fait = progres.load(Ordering::Relaxed).min(cli.echantillons as u64);
+                    barre.set_position(fait);
+                    if fait >= cli.echantillons as u64 {
+                        barre.finish();
+                    } else {
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                }
+            });
+        });
+
+        let mut pixels = vec![0; bords.0 * bords.1];
+        normaliser_buddhabrot(&compteurs, &mut pixels);
+
+        ecrire_image(&cli.sortie, &pixels, bords, ColorType::Gray(8), format)
+            .expect("Impossible d'écrire l'image");
+        return;
+    }
+
+    let mut pixels = vec![0; 3 * bords.0 * bords.1];
+    let lig_par_bande = bords.1 / cli.threads + 1;
+    let progres = AtomicU64::new(0);
+    let barre = barre_progression(bords.1 as u64);
+    let config = RenderConfig {
+        fractal: cli.fractal,
+        palette: cli.palette,
+        iterations: cli.iterations,
+    };
 
     {
-        let bandes: Vec<&mut [u8]> = 
-            pixels.chunks_mut(lig_par_bande * bords.0).collect();
+        let bandes: Vec<&mut [u8]> =
+            pixels.chunks_mut(lig_par_bande * bords.0 * 3).collect();
         crossbeam::scope(|spawner| {
             for (i, bande) in bandes.into_iter().enumerate() {
                 let top = lig_par_bande * i;
-                let haute = bande.len() / bords.0;
+                let haute = bande.len() / (bords.0 * 3);
                 let bande_bords = (bords.0, haute);
                 let bande_supg = pixel_en_point(bords, (0, top), super_ga, infer_dr);
                 let bande_infd = pixel_en_point(bords, (bords.0, top + haute), super_ga, infer_dr);
+                let progres = &progres;
+                let config = &config;
 
                 spawner.spawn(move || {
-                    render(bande, bande_bords, bande_supg, bande_infd)
+                    render(bande, bande_bords, bande_supg, bande_infd, config, progres)
                 });
             }
+
+            spawner.spawn(|| {
+                while !barre.is_finished() {
+                    let fait = progres.load(Ordering::Relaxed).min(bords.1 as u64);
+                    barre.set_position(fait);
+                    if fait >= bords.1 as u64 {
+                        barre.finish();
+                    } else {
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                }
+            });
         });
     }
 
-    ecrire_image(&args[1], &pixels, bords)
+    ecrire_image(&cli.sortie, &pixels, bords, ColorType::RGB(8), format)
         .expect("Impossible d'écrire l'image");
 }
\ No newline at end of file